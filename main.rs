@@ -1,25 +1,20 @@
 mod statemachine;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 fn main() {
     println!("--- Starting Rust State Machine ---");
 
-    // 1. Initialize the machine
+    // 1. Initialize the machine (drives itself off the system clock)
     let mut sm = statemachine::StateMachine::new();
 
-    // 2. Start the clock
-    let start_time = Instant::now();
-
     println!("{}", sm.get_state_str());
 
     while sm.is_running() {
-        // 3. Update time (Seconds as f64)
-        let elapsed = start_time.elapsed();
-        sm.ctx.now = elapsed.as_secs_f64();
+        // 2. Poll step: the machine reads its own clock and drains however
+        // much time passed in fixed_dt steps.
+        sm.update();
 
-        // 4. Tick the machine
-        sm.tick();
         sm.ctx.counter += 1;
         println!(
             "{:02}: {}",
@@ -28,13 +23,13 @@ fn main() {
             //sm.ctx.do_loop
         );
 
-        // 5. Introspection (Optional debug print)
+        // 3. Introspection (Optional debug print)
         // Note: We only print if the state string changes to avoid spam,
         // or you can rely on the hooks inside the machine.
         // let state_str = sm.get_state_str();
         // println!("Current: {}", state_str);
 
-        // 6. Sleep to prevent 100% CPU usage
+        // 4. Sleep to prevent 100% CPU usage
         thread::sleep(Duration::from_millis(10));
     }
 }
@@ -0,0 +1,577 @@
+//! Hand-rolled state machine driving the demo in `main`.
+//!
+//! This mirrors what the generator is expected to emit: a `Context` struct
+//! carrying shared data, a `State` enum, and a `StateMachine` that ticks
+//! the active state forward.
+
+// This module is generated (or hand-maintained as a stand-in for the
+// generator) surface: `main` only drives a small slice of it, and the rest
+// — e.g. `ManualClock`, event variants, the diagram exporters — is exercised
+// by the tests below rather than by the demo binary.
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Source of the time base the machine advances on.
+///
+/// Production code drives the machine with [`SystemClock`]; tests swap in
+/// [`ManualClock`] to advance time by hand and assert the exact state
+/// sequence a given timeline produces.
+pub trait Clock {
+    /// Seconds elapsed since the clock was started, as a float.
+    fn now(&self) -> f64;
+}
+
+/// Wall-clock time measured from construction, via `Instant`.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// A clock whose value is set explicitly by the caller, for tests.
+#[derive(Default)]
+pub struct ManualClock {
+    now: f64,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward by `dt` seconds.
+    pub fn advance(&mut self, dt: f64) {
+        self.now += dt;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.now
+    }
+}
+
+/// Data carried between ticks and visible to transition guards.
+#[derive(Debug, Default)]
+pub struct Context {
+    pub now: f64,
+    pub counter: u32,
+}
+
+/// The states of the generated machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    Running,
+    Paused,
+    Done,
+}
+
+/// External inputs that can trigger a transition between ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Skip straight to `Running`, without waiting on the `Idle` timer.
+    Start,
+    /// Return to `Idle` from any state.
+    Reset,
+    /// Suspend the active state and push `Paused` on top of it.
+    Pause,
+    /// Pop `Paused` and resume whatever was running underneath.
+    Resume,
+}
+
+/// A state on the stack together with when it was entered, so a declarative
+/// timeout can measure `ctx.now - entered_at` against its duration.
+struct Frame {
+    state: State,
+    entered_at: f64,
+}
+
+pub struct StateMachine {
+    pub ctx: Context,
+    /// States currently active, outermost first and top of stack last
+    /// (`.last()`). A child
+    /// state pushed on top can be popped later to resume its parent
+    /// exactly where it left off.
+    stack: Vec<Frame>,
+    clock: Box<dyn Clock>,
+    pending_events: VecDeque<Event>,
+    last_trigger: Option<Event>,
+    /// The step size `update()` advances the machine by, regardless of how
+    /// often the caller polls it.
+    pub fixed_dt: Duration,
+    /// Real time banked between fixed steps.
+    accumulator: Duration,
+    /// `clock.now()` as of the last `update()` call, so `update()` can
+    /// derive how much clock time has passed without the caller having to
+    /// track its own `Instant`.
+    last_poll: f64,
+    /// Records every `on_enter`/`on_exit` invocation in order, so tests can
+    /// assert the lifecycle hooks fire exactly once per activation and
+    /// deactivation without needing per-state side effects to observe.
+    transitions_log: Vec<(&'static str, State)>,
+}
+
+impl StateMachine {
+    /// Builds a machine driven by the real system clock.
+    pub fn new() -> Self {
+        Self::with_clock(Box::new(SystemClock::new()))
+    }
+
+    /// Builds a machine driven by an arbitrary [`Clock`], e.g. a
+    /// [`ManualClock`] in tests.
+    pub fn with_clock(clock: Box<dyn Clock>) -> Self {
+        let last_poll = clock.now();
+        Self {
+            ctx: Context::default(),
+            stack: vec![Frame { state: State::Idle, entered_at: 0.0 }],
+            clock,
+            pending_events: VecDeque::new(),
+            last_trigger: None,
+            fixed_dt: Duration::from_millis(10),
+            accumulator: Duration::ZERO,
+            last_poll,
+            transitions_log: Vec::new(),
+        }
+    }
+
+    /// The active state, i.e. the top of the stack.
+    fn state(&self) -> State {
+        self.top().state
+    }
+
+    fn top(&self) -> &Frame {
+        self.stack.last().expect("state stack is never empty while running")
+    }
+
+    /// `after(duration) => next` declared for each state: how long a state
+    /// must have been active on top of the stack before it times out.
+    fn timeout_for(state: State) -> Option<(f64, State)> {
+        match state {
+            State::Idle => Some((1.0, State::Running)),
+            State::Running => Some((2.0, State::Done)),
+            State::Paused | State::Done => None,
+        }
+    }
+
+    /// `false` once the stack empties *or* the active state is the
+    /// terminal `Done` leaf — `Done` never pops itself, so without the
+    /// second check the machine would sit there ticking forever.
+    pub fn is_running(&self) -> bool {
+        !self.stack.is_empty() && self.state() != State::Done
+    }
+
+    /// Renders the full stack, outermost state first, so nested state is
+    /// visible for debugging (e.g. `"Running > Paused"`).
+    pub fn get_state_str(&self) -> String {
+        self.stack
+            .iter()
+            .map(|frame| format!("{:?}", frame.state))
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+
+    /// Queues `event` to be consumed on the next step, i.e. the next
+    /// `tick()` or `fixed_update()` (which `update()` runs).
+    pub fn post(&mut self, event: Event) {
+        self.pending_events.push_back(event);
+    }
+
+    /// The event that caused the most recent state change, if any — `None`
+    /// when the last transition (if there was one) fired on a time guard.
+    pub fn last_trigger(&self) -> Option<Event> {
+        self.last_trigger
+    }
+
+    /// Suspends the current top-of-stack state and activates `state` above
+    /// it, without running the parent's `on_exit`.
+    pub fn push(&mut self, state: State) {
+        self.on_enter(state);
+        self.stack.push(Frame { state, entered_at: self.ctx.now });
+    }
+
+    /// Deactivates the top-of-stack state and resumes whichever state is
+    /// beneath it, if any. The time spent in the popped frame is added to
+    /// the resumed frame's `entered_at`, so its timeout is measured as if
+    /// the popped overlay had never been active — resuming picks up
+    /// exactly where it left off rather than having lost time to whatever
+    /// was pushed on top of it.
+    pub fn pop(&mut self) -> Option<State> {
+        let popped = self.stack.pop()?;
+        self.on_exit(popped.state);
+        if let Some(resumed) = self.stack.last_mut() {
+            resumed.entered_at += self.ctx.now - popped.entered_at;
+        }
+        Some(popped.state)
+    }
+
+    /// Replaces the top-of-stack state with `next`, running `on_exit`/
+    /// `on_enter` as usual. Equivalent to what a guard-driven transition
+    /// does, but callable directly.
+    pub fn replace(&mut self, next: State) {
+        self.transition(next, None);
+    }
+
+    /// Lightweight poll step: call once per outer loop iteration. Reads how
+    /// much time has passed on the injected [`Clock`] since the last call,
+    /// banks it in an accumulator, and drains it in `fixed_dt`-sized steps
+    /// via `fixed_update`, so the machine advances on a steady cadence
+    /// regardless of how often the caller spins.
+    pub fn update(&mut self) {
+        let now = self.clock.now();
+        let frame_dt = Duration::from_secs_f64((now - self.last_poll).max(0.0));
+        self.last_poll = now;
+
+        self.accumulator += frame_dt;
+        while self.accumulator >= self.fixed_dt {
+            self.fixed_update(self.fixed_dt);
+            self.accumulator -= self.fixed_dt;
+        }
+    }
+
+    /// One deterministic simulation step: advances `ctx.now` by exactly
+    /// `dt` (it does *not* re-read the clock), then drains events and
+    /// evaluates timeouts against that advanced time. This is what makes
+    /// the cadence deterministic regardless of how often `update()` is
+    /// polled, and why a `ManualClock` in tests sees exactly the `dt`
+    /// steps it's driven with.
+    pub fn fixed_update(&mut self, dt: Duration) {
+        self.ctx.now += dt.as_secs_f64();
+        self.step();
+    }
+
+    /// Pulls the current time from the clock, then runs one step. Prefer
+    /// `update()`/`fixed_update()` for a deterministic cadence; call this
+    /// directly only when ticking straight off real time is intended.
+    pub fn tick(&mut self) {
+        self.ctx.now = self.clock.now();
+        self.step();
+    }
+
+    /// Drains queued events, then evaluates timeouts against `ctx.now`.
+    /// Only the top of the stack is advanced; a manual or event-driven
+    /// transition earlier in the step resets `entered_at`, which cancels
+    /// any timeout that was pending for the state it left.
+    fn step(&mut self) {
+        let mut events = std::mem::take(&mut self.pending_events);
+        self.handle_events(events.make_contiguous());
+
+        let (state, entered_at) = (self.top().state, self.top().entered_at);
+        let timed_out = Self::timeout_for(state).filter(|(timeout, _)| self.ctx.now - entered_at >= *timeout);
+        if let Some((_, next)) = timed_out {
+            self.transition(next, None);
+        }
+    }
+
+    /// Lets the active (top-of-stack) state consume queued events and fire
+    /// event-based transitions before the time-based guards run.
+    pub fn handle_events(&mut self, events: &[Event]) {
+        for &event in events {
+            match (self.state(), event) {
+                (_, Event::Reset) => self.reset_to_idle(event),
+                (State::Idle, Event::Start) => self.transition(State::Running, Some(event)),
+                (State::Paused, Event::Resume) => {
+                    self.pop();
+                    self.last_trigger = Some(event);
+                }
+                (state, Event::Pause) if state != State::Paused => {
+                    self.push(State::Paused);
+                    self.last_trigger = Some(event);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Unwinds the whole stack — not just the top frame — back down to a
+    /// single `Idle`, running `on_exit` for every active frame from the
+    /// top down. `Reset` is defined as returning to `Idle` "from any
+    /// state", which includes any substates pushed on top of it.
+    fn reset_to_idle(&mut self, trigger: Event) {
+        while let Some(frame) = self.stack.pop() {
+            self.on_exit(frame.state);
+        }
+        self.on_enter(State::Idle);
+        self.stack.push(Frame { state: State::Idle, entered_at: self.ctx.now });
+        self.last_trigger = Some(trigger);
+    }
+
+    /// Replaces the top-of-stack state with `next`, running `on_exit` for
+    /// the source state and `on_enter` for the target before anything else
+    /// observes the new state, and resetting `entered_at` so timeouts are
+    /// measured from the moment `next` became active. Runs the full
+    /// exit/enter cycle even when `next` is the state already active, so a
+    /// re-entry (e.g. `replace(State::Running)` while already `Running`) restarts its
+    /// timeout instead of leaving the stale `entered_at` in place.
+    fn transition(&mut self, next: State, trigger: Option<Event>) {
+        self.on_exit(self.state());
+        self.on_enter(next);
+        *self.stack.last_mut().expect("state stack is never empty while running") =
+            Frame { state: next, entered_at: self.ctx.now };
+        self.last_trigger = trigger;
+    }
+
+    /// Runs once each time `state` is activated (by a transition, a push or
+    /// a reset), just before its frame is placed on the stack. Generated states
+    /// start out with nothing to do here beyond recording the call; fill in
+    /// per-state setup as needed.
+    fn on_enter(&mut self, state: State) {
+        self.transitions_log.push(("enter", state));
+    }
+
+    /// Runs once each time `state` is deactivated: just before its frame is
+    /// replaced on a transition, and just after its frame is removed from
+    /// the stack on a pop or a reset. Generated states
+    /// start out with nothing to do here beyond recording the call; fill in
+    /// per-state teardown as needed.
+    fn on_exit(&mut self, state: State) {
+        self.transitions_log.push(("exit", state));
+    }
+
+    fn all_states() -> [State; 4] {
+        [State::Idle, State::Running, State::Paused, State::Done]
+    }
+
+    /// The event-triggered edges out of `state`, for introspection, each
+    /// labeled with the event that fires it. Mirrors the guards in
+    /// `handle_events` exactly, including that `Pause` has no edge from
+    /// `Paused` (pausing an already-paused state is a no-op there too).
+    ///
+    /// The `Resume` edge is drawn back to `Idle` as a stand-in — the real
+    /// target is whatever state was beneath `Paused` on the stack, which
+    /// isn't known statically — and the label says so explicitly.
+    fn event_edges(state: State) -> Vec<(String, State)> {
+        let mut edges = vec![(format!("{:?}", Event::Reset), State::Idle)];
+        match state {
+            State::Idle => edges.push((format!("{:?}", Event::Start), State::Running)),
+            State::Paused => edges.push(("Resume (target is dynamic; shown as Idle)".to_string(), State::Idle)),
+            _ => {}
+        }
+        if state != State::Paused {
+            edges.push((format!("{:?}", Event::Pause), State::Paused));
+        }
+        edges
+    }
+
+    /// Renders every state and transition (event-triggered and timed) as a
+    /// Graphviz `digraph`, marking the initial state and highlighting the
+    /// one currently active.
+    pub fn to_dot(&self) -> String {
+        let current = self.state();
+        let mut out = String::from("digraph StateMachine {\n");
+
+        for state in Self::all_states() {
+            let mut attrs = Vec::new();
+            if state == State::Idle {
+                attrs.push("shape=doublecircle".to_string());
+            }
+            if state == current {
+                attrs.push("style=filled".to_string());
+                attrs.push("fillcolor=lightgrey".to_string());
+            }
+            if attrs.is_empty() {
+                out.push_str(&format!("  {:?};\n", state));
+            } else {
+                out.push_str(&format!("  {:?} [{}];\n", state, attrs.join(", ")));
+            }
+        }
+
+        for state in Self::all_states() {
+            if let Some((timeout, next)) = Self::timeout_for(state) {
+                out.push_str(&format!(
+                    "  {:?} -> {:?} [label=\"after {}s\"];\n",
+                    state, next, timeout
+                ));
+            }
+            for (label, next) in Self::event_edges(state) {
+                out.push_str(&format!("  {:?} -> {:?} [label=\"{}\"];\n", state, next, label));
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the same states and transitions as a PlantUML state diagram.
+    pub fn to_plantuml(&self) -> String {
+        let current = self.state();
+        let mut out = String::from("@startuml\n[*] --> Idle\n");
+
+        for state in Self::all_states() {
+            if state == current {
+                out.push_str(&format!("state {:?} #lightgrey\n", state));
+            }
+        }
+
+        for state in Self::all_states() {
+            if let Some((timeout, next)) = Self::timeout_for(state) {
+                out.push_str(&format!("{:?} --> {:?} : after {}s\n", state, next, timeout));
+            }
+            for (label, next) in Self::event_edges(state) {
+                out.push_str(&format!("{:?} --> {:?} : {}\n", state, next, label));
+            }
+        }
+
+        out.push_str("@enduml\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Lets a test hold onto a [`ManualClock`] and advance it after it's
+    /// been boxed inside a [`StateMachine`].
+    struct SharedManualClock(Rc<RefCell<ManualClock>>);
+
+    impl Clock for SharedManualClock {
+        fn now(&self) -> f64 {
+            self.0.borrow().now()
+        }
+    }
+
+    #[test]
+    fn manual_clock_drives_idle_through_running_to_done() {
+        let clock = Rc::new(RefCell::new(ManualClock::new()));
+        let mut sm = StateMachine::with_clock(Box::new(SharedManualClock(clock.clone())));
+        assert_eq!(sm.get_state_str(), "Idle");
+        assert!(sm.is_running());
+
+        clock.borrow_mut().advance(1.0);
+        sm.tick();
+        assert_eq!(sm.get_state_str(), "Running");
+
+        clock.borrow_mut().advance(2.0);
+        sm.tick();
+        assert_eq!(sm.get_state_str(), "Done");
+        assert!(!sm.is_running());
+    }
+
+    #[test]
+    fn posted_event_fires_before_the_timeout_and_sets_last_trigger() {
+        let mut sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        assert_eq!(sm.last_trigger(), None);
+
+        sm.post(Event::Start);
+        sm.tick();
+
+        assert_eq!(sm.get_state_str(), "Running");
+        assert_eq!(sm.last_trigger(), Some(Event::Start));
+    }
+
+    #[test]
+    fn replace_jumps_directly_to_a_state() {
+        let mut sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        sm.replace(State::Running);
+        assert_eq!(sm.get_state_str(), "Running");
+    }
+
+    #[test]
+    fn reset_collapses_the_whole_stack_to_idle() {
+        let mut sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        sm.push(State::Running);
+        sm.push(State::Paused);
+        assert_eq!(sm.get_state_str(), "Idle > Running > Paused");
+
+        sm.post(Event::Reset);
+        sm.tick();
+
+        assert_eq!(sm.get_state_str(), "Idle");
+        assert!(sm.is_running());
+        assert_eq!(sm.last_trigger(), Some(Event::Reset));
+    }
+
+    #[test]
+    fn pause_while_already_paused_is_a_no_op() {
+        let mut sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        sm.push(State::Paused);
+
+        sm.post(Event::Pause);
+        sm.tick();
+
+        assert_eq!(sm.get_state_str(), "Idle > Paused");
+        assert_eq!(sm.last_trigger(), None);
+    }
+
+    #[test]
+    fn on_enter_and_on_exit_fire_exactly_once_per_activation_and_deactivation() {
+        let mut sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        assert_eq!(sm.transitions_log, Vec::new());
+
+        // A normal transition exits the source state and enters the target.
+        sm.replace(State::Running);
+        assert_eq!(sm.transitions_log, vec![("exit", State::Idle), ("enter", State::Running)]);
+        sm.transitions_log.clear();
+
+        // Pushing a substate enters it without exiting the state beneath.
+        sm.push(State::Paused);
+        assert_eq!(sm.transitions_log, vec![("enter", State::Paused)]);
+        sm.transitions_log.clear();
+
+        // Popping it back off exits it without re-entering the resumed parent.
+        sm.pop();
+        assert_eq!(sm.transitions_log, vec![("exit", State::Paused)]);
+        sm.transitions_log.clear();
+
+        // A reset from a multi-level stack exits every active frame from the
+        // top down, then enters `Idle` exactly once.
+        sm.push(State::Paused);
+        sm.transitions_log.clear();
+        sm.post(Event::Reset);
+        sm.tick();
+        assert_eq!(
+            sm.transitions_log,
+            vec![("exit", State::Paused), ("exit", State::Running), ("enter", State::Idle)]
+        );
+    }
+
+    #[test]
+    fn to_dot_marks_the_initial_and_current_state_and_labels_transitions() {
+        let sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        let dot = sm.to_dot();
+
+        assert!(dot.starts_with("digraph StateMachine {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("Idle [shape=doublecircle, style=filled, fillcolor=lightgrey];"));
+        assert!(dot.contains("Idle -> Running [label=\"after 1s\"];"));
+        assert!(dot.contains("Idle -> Running [label=\"Start\"];"));
+        assert!(dot.contains("Running -> Done [label=\"after 2s\"];"));
+        assert!(dot.contains("Paused -> Idle [label=\"Resume (target is dynamic; shown as Idle)\"];"));
+        assert!(!dot.contains("Paused -> Paused"));
+    }
+
+    #[test]
+    fn to_plantuml_marks_the_current_state_and_labels_transitions() {
+        let sm = StateMachine::with_clock(Box::new(ManualClock::new()));
+        let uml = sm.to_plantuml();
+
+        assert!(uml.starts_with("@startuml\n[*] --> Idle\n"));
+        assert!(uml.ends_with("@enduml\n"));
+        assert!(uml.contains("state Idle #lightgrey\n"));
+        assert!(uml.contains("Idle --> Running : after 1s\n"));
+        assert!(uml.contains("Idle --> Running : Start\n"));
+        assert!(uml.contains("Paused --> Idle : Resume (target is dynamic; shown as Idle)\n"));
+        assert!(!uml.contains("Paused --> Paused"));
+    }
+}